@@ -0,0 +1,197 @@
+// html5lib-tests conformance runner.
+//
+// Parses html5lib `.dat` tree-construction test files and checks that
+// `parse_document`/`parse_fragment` plus `dom_to_test_format` reproduce the
+// expected serialization for each test case. Point it at one or more
+// directories or files; it defaults to `../html5lib-tests/tree-construction`
+// next to this crate.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use html5ever::tendril::TendrilSink;
+use html5ever::{namespace_url, ns, parse_document, parse_fragment, QualName};
+use markup5ever_rcdom::RcDom;
+use rust_benchmark::{dom_to_test_format, fragment_to_test_format};
+
+const DIRECTIVES: &[&str] = &[
+    "#data",
+    "#errors",
+    "#new-errors",
+    "#document-fragment",
+    "#script-on",
+    "#script-off",
+    "#document",
+];
+
+struct TestCase {
+    data: String,
+    document_fragment: Option<String>,
+    expected: String,
+}
+
+/// Split a `.dat` file's contents into individual `#data`/`#document` blocks.
+fn parse_dat_file(content: &str) -> Vec<TestCase> {
+    let mut cases = Vec::new();
+    let mut lines = content.lines().peekable();
+
+    while let Some(line) = lines.next() {
+        if line != "#data" {
+            continue;
+        }
+
+        let mut data_lines = Vec::new();
+        while let Some(&next) = lines.peek() {
+            if DIRECTIVES.contains(&next) {
+                break;
+            }
+            data_lines.push(next);
+            lines.next();
+        }
+
+        let mut document_fragment = None;
+        let mut expected_lines: Vec<&str> = Vec::new();
+
+        while let Some(&next) = lines.peek() {
+            if next == "#data" {
+                break;
+            }
+            lines.next();
+            match next {
+                "#errors" | "#new-errors" => {
+                    while let Some(&after) = lines.peek() {
+                        if DIRECTIVES.contains(&after) {
+                            break;
+                        }
+                        lines.next();
+                    }
+                }
+                "#document-fragment" => {
+                    document_fragment = lines.next().map(str::to_string);
+                }
+                "#script-on" | "#script-off" => {}
+                "#document" => {
+                    while let Some(&after) = lines.peek() {
+                        if after == "#data" {
+                            break;
+                        }
+                        expected_lines.push(after);
+                        lines.next();
+                    }
+                    // The blank line separating this test from the next one
+                    // gets swept up above; it's not part of the expected
+                    // tree, which `dom_to_test_format` never trails with one.
+                    while expected_lines.last() == Some(&"") {
+                        expected_lines.pop();
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        cases.push(TestCase {
+            data: data_lines.join("\n"),
+            document_fragment,
+            expected: expected_lines.join("\n"),
+        });
+    }
+
+    cases
+}
+
+/// Resolve a `#document-fragment` context like `div`, `svg path`, or
+/// `math mtext` into the namespaced context element html5ever expects.
+fn parse_context(context: &str) -> QualName {
+    let mut parts = context.split_whitespace();
+    match parts.next() {
+        Some("svg") => QualName::new(None, ns!(svg), parts.next().unwrap_or("svg").into()),
+        Some("math") => QualName::new(None, ns!(mathml), parts.next().unwrap_or("math").into()),
+        _ => QualName::new(None, ns!(html), context.into()),
+    }
+}
+
+/// Run a single test case through the appropriate parse entry point.
+fn run_case(case: &TestCase) -> String {
+    if let Some(context) = &case.document_fragment {
+        let context_name = parse_context(context);
+        let dom = parse_fragment(RcDom::default(), Default::default(), context_name, Vec::new())
+            .from_utf8()
+            .read_from(&mut case.data.as_bytes())
+            .unwrap();
+        fragment_to_test_format(&dom)
+    } else {
+        let dom = parse_document(RcDom::default(), Default::default())
+            .from_utf8()
+            .read_from(&mut case.data.as_bytes())
+            .unwrap();
+        dom_to_test_format(&dom)
+    }
+}
+
+fn collect_dat_files(path: &Path, out: &mut Vec<PathBuf>) {
+    if path.is_dir() {
+        let Ok(entries) = fs::read_dir(path) else {
+            return;
+        };
+        let mut children: Vec<_> = entries.flatten().map(|entry| entry.path()).collect();
+        children.sort();
+        for child in children {
+            collect_dat_files(&child, out);
+        }
+    } else if path.extension().map(|e| e == "dat").unwrap_or(false) {
+        out.push(path.to_path_buf());
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let paths: Vec<PathBuf> = if args.is_empty() {
+        let script_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+        vec![script_dir.join("../html5lib-tests/tree-construction")]
+    } else {
+        args.into_iter().map(PathBuf::from).collect()
+    };
+
+    let mut dat_files = Vec::new();
+    for path in &paths {
+        collect_dat_files(path, &mut dat_files);
+    }
+
+    if dat_files.is_empty() {
+        eprintln!("No .dat files found in {:?}", paths);
+        std::process::exit(1);
+    }
+
+    let mut total = 0;
+    let mut failures = Vec::new();
+
+    for file in &dat_files {
+        let content = fs::read_to_string(file).expect("failed to read test file");
+        for (index, case) in parse_dat_file(&content).into_iter().enumerate() {
+            total += 1;
+            let actual = run_case(&case);
+            if actual != case.expected {
+                failures.push((file.clone(), index, case, actual));
+            }
+        }
+    }
+
+    println!(
+        "{} passed, {} failed, {} total",
+        total - failures.len(),
+        failures.len(),
+        total
+    );
+
+    for (file, index, case, actual) in &failures {
+        println!("--- FAIL: {} [test #{}] ---", file.display(), index);
+        println!("input:\n{}", case.data);
+        println!("expected:\n{}", case.expected);
+        println!("actual:\n{}", actual);
+    }
+
+    if !failures.is_empty() {
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,33 @@
+// Prints every resource-referencing URL discovered in one or more parsed
+// HTML documents (links, scripts, images, inline CSS, meta refresh, etc.).
+//
+// Usage: resources <file.html> [file.html ...]
+
+use std::env;
+use std::fs;
+
+use html5ever::parse_document;
+use html5ever::tendril::TendrilSink;
+use markup5ever_rcdom::RcDom;
+use rust_benchmark::collect_resources;
+
+fn main() {
+    let paths: Vec<String> = env::args().skip(1).collect();
+    if paths.is_empty() {
+        eprintln!("usage: resources <file.html> [file.html ...]");
+        std::process::exit(1);
+    }
+
+    for path in paths {
+        let html = fs::read_to_string(&path).expect("failed to read input file");
+        let dom = parse_document(RcDom::default(), Default::default())
+            .from_utf8()
+            .read_from(&mut html.as_bytes())
+            .unwrap();
+
+        println!("{}:", path);
+        for resource in collect_resources(&dom) {
+            println!("  <{}> {}", resource.element, resource.url);
+        }
+    }
+}
@@ -0,0 +1,275 @@
+// Shared DOM serialization helpers used by the benchmark binary and the
+// html5lib-tests conformance runner.
+
+use std::collections::HashSet;
+use std::iter;
+
+use markup5ever_rcdom::{Handle, NodeData, RcDom};
+
+/// Serialize a DOM subtree to the html5lib tree-construction test format.
+pub fn serialize_to_test_format(buf: &mut String, indent: usize, handle: &Handle) {
+    buf.push('|');
+    buf.extend(iter::repeat_n(" ", indent));
+
+    match &handle.data {
+        NodeData::Document => {
+            // Don't output anything for document node, just recurse into children
+            for child in handle.children.borrow().iter() {
+                serialize_to_test_format(buf, indent, child);
+            }
+            return;
+        }
+
+        NodeData::Doctype {
+            name,
+            public_id,
+            system_id,
+        } => {
+            buf.push_str("<!DOCTYPE ");
+            buf.push_str(name);
+            if !public_id.is_empty() || !system_id.is_empty() {
+                buf.push_str(&format!(" \"{}\" \"{}\"", public_id, system_id));
+            }
+            buf.push_str(">\n");
+        }
+
+        NodeData::Text { contents } => {
+            buf.push('"');
+            buf.push_str(&contents.borrow());
+            buf.push_str("\"\n");
+        }
+
+        NodeData::Comment { contents } => {
+            buf.push_str("<!-- ");
+            buf.push_str(contents);
+            buf.push_str(" -->\n");
+        }
+
+        NodeData::Element { name, attrs, template_contents, .. } => {
+            buf.push('<');
+            match &name.ns {
+                ns if *ns == html5ever::ns!(svg) => buf.push_str("svg "),
+                ns if *ns == html5ever::ns!(mathml) => buf.push_str("math "),
+                _ => (),
+            }
+            buf.push_str(&name.local);
+            buf.push_str(">\n");
+
+            let mut attrs_vec: Vec<_> = attrs.borrow().clone();
+            attrs_vec.sort_by(|x, y| x.name.local.cmp(&y.name.local));
+
+            for attr in attrs_vec.iter() {
+                buf.push('|');
+                buf.extend(iter::repeat_n(" ", indent + 2));
+                match &attr.name.ns {
+                    ns if *ns == html5ever::ns!(xlink) => buf.push_str("xlink "),
+                    ns if *ns == html5ever::ns!(xml) => buf.push_str("xml "),
+                    ns if *ns == html5ever::ns!(xmlns) => buf.push_str("xmlns "),
+                    _ => (),
+                }
+                buf.push_str(&format!("{}=\"{}\"\n", attr.name.local, attr.value));
+            }
+
+            // Recurse into children
+            for child in handle.children.borrow().iter() {
+                serialize_to_test_format(buf, indent + 2, child);
+            }
+
+            // Handle template contents
+            if let Some(ref content) = &*template_contents.borrow() {
+                buf.push('|');
+                buf.extend(iter::repeat_n(" ", indent + 2));
+                buf.push_str("content\n");
+                for child in content.children.borrow().iter() {
+                    serialize_to_test_format(buf, indent + 4, child);
+                }
+            }
+            return; // Already handled children
+        }
+
+        NodeData::ProcessingInstruction { .. } => {}
+    }
+
+    // Recurse into children (for non-element nodes)
+    for child in handle.children.borrow().iter() {
+        serialize_to_test_format(buf, indent + 2, child);
+    }
+}
+
+/// Serialize a full parsed document to the html5lib test format.
+pub fn dom_to_test_format(dom: &RcDom) -> String {
+    let mut result = String::new();
+    for child in dom.document.children.borrow().iter() {
+        serialize_to_test_format(&mut result, 1, child);
+    }
+    // Remove trailing newline if present
+    if result.ends_with('\n') {
+        result.pop();
+    }
+    result
+}
+
+/// Serialize a parsed fragment to the html5lib test format. `parse_fragment`
+/// returns a `Document` whose single child is a synthesized root element
+/// holding the fragment's nodes, so this starts at that root's children
+/// rather than the `Document` node (unlike `dom_to_test_format`).
+pub fn fragment_to_test_format(dom: &RcDom) -> String {
+    let mut result = String::new();
+    let document_children = dom.document.children.borrow();
+    if let Some(root) = document_children.first() {
+        for child in root.children.borrow().iter() {
+            serialize_to_test_format(&mut result, 1, child);
+        }
+    }
+    // Remove trailing newline if present
+    if result.ends_with('\n') {
+        result.pop();
+    }
+    result
+}
+
+/// A resource-referencing attribute found on some element in the tree.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ResourceRef {
+    /// Local name of the owning element, e.g. `"img"` or `"link"`.
+    pub element: String,
+    /// The referenced URL as written in the source (not resolved against any
+    /// base URL), with surrounding whitespace and, for CSS `url(...)` and
+    /// meta refresh targets, surrounding quotes removed.
+    pub url: String,
+}
+
+/// Walk a parsed document collecting every resource-referencing attribute:
+/// `href`, `src`, `srcset`, `poster`, `action`, CSS `url(...)` inside inline
+/// `style` attributes, and `<meta http-equiv=refresh>` targets. Descends
+/// into `template_contents` the same way `serialize_to_test_format` does, so
+/// resources inside `<template>` aren't missed.
+pub fn collect_resources(dom: &RcDom) -> Vec<ResourceRef> {
+    let mut seen = HashSet::new();
+    let mut resources = Vec::new();
+    for child in dom.document.children.borrow().iter() {
+        collect_resources_from_handle(child, &mut seen, &mut resources);
+    }
+    resources
+}
+
+fn collect_resources_from_handle(
+    handle: &Handle,
+    seen: &mut HashSet<ResourceRef>,
+    out: &mut Vec<ResourceRef>,
+) {
+    if let NodeData::Element {
+        name,
+        attrs,
+        template_contents,
+        ..
+    } = &handle.data
+    {
+        let element = name.local.to_string();
+        let attrs_vec = attrs.borrow();
+
+        let is_meta_refresh = element == "meta"
+            && attrs_vec.iter().any(|a| {
+                (&*a.name.local).eq_ignore_ascii_case("http-equiv")
+                    && (&*a.value).eq_ignore_ascii_case("refresh")
+            });
+
+        for attr in attrs_vec.iter() {
+            match &*attr.name.local {
+                "href" | "src" | "poster" | "action" => {
+                    push_resource(seen, out, &element, &attr.value);
+                }
+                "srcset" => {
+                    for url in parse_srcset(&attr.value) {
+                        push_resource(seen, out, &element, &url);
+                    }
+                }
+                "style" => {
+                    for url in extract_css_urls(&attr.value) {
+                        push_resource(seen, out, &element, &url);
+                    }
+                }
+                "content" if is_meta_refresh => {
+                    if let Some(url) = parse_refresh_target(&attr.value) {
+                        push_resource(seen, out, &element, &url);
+                    }
+                }
+                _ => {}
+            }
+        }
+        drop(attrs_vec);
+
+        for child in handle.children.borrow().iter() {
+            collect_resources_from_handle(child, seen, out);
+        }
+
+        if let Some(ref content) = &*template_contents.borrow() {
+            for child in content.children.borrow().iter() {
+                collect_resources_from_handle(child, seen, out);
+            }
+        }
+        return;
+    }
+
+    for child in handle.children.borrow().iter() {
+        collect_resources_from_handle(child, seen, out);
+    }
+}
+
+fn push_resource(seen: &mut HashSet<ResourceRef>, out: &mut Vec<ResourceRef>, element: &str, url: &str) {
+    let url = url.trim();
+    if url.is_empty() {
+        return;
+    }
+    let resource = ResourceRef {
+        element: element.to_string(),
+        url: url.to_string(),
+    };
+    if seen.insert(resource.clone()) {
+        out.push(resource);
+    }
+}
+
+/// Extract the URL candidate from each comma-separated entry of a `srcset`.
+fn parse_srcset(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .filter_map(|candidate| candidate.trim().split_whitespace().next())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Extract every `url(...)` target from an inline CSS `style` attribute.
+fn extract_css_urls(css: &str) -> Vec<String> {
+    let lower = css.to_ascii_lowercase();
+    let mut urls = Vec::new();
+    let mut search_start = 0;
+
+    while let Some(rel_idx) = lower[search_start..].find("url(") {
+        let start = search_start + rel_idx + "url(".len();
+        let Some(rel_end) = css[start..].find(')') else {
+            break;
+        };
+        let end = start + rel_end;
+        let raw = css[start..end].trim().trim_matches(['\'', '"']);
+        if !raw.is_empty() {
+            urls.push(raw.to_string());
+        }
+        search_start = end + 1;
+    }
+
+    urls
+}
+
+/// Parse the `url=` target out of a `<meta http-equiv=refresh content=...>` value.
+fn parse_refresh_target(content: &str) -> Option<String> {
+    let lower = content.to_ascii_lowercase();
+    let idx = lower.find("url=")?;
+    let raw = content[idx + "url=".len()..].trim();
+    let trimmed = raw.trim_matches(['\'', '"']);
+    if trimmed.is_empty() {
+        None
+    } else {
+        Some(trimmed.to_string())
+    }
+}
@@ -1,174 +1,194 @@
 // Benchmark for html5ever - outputs JSON for comparison with other implementations
 
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::collections::HashMap;
+use std::env;
 use std::fs;
-use std::iter;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::Instant;
 
 use html5ever::tendril::TendrilSink;
-use html5ever::parse_document;
-use markup5ever_rcdom::{Handle, NodeData, RcDom};
-use serde::Serialize;
+use html5ever::{parse_document, parse_fragment, QualName};
+use markup5ever_rcdom::RcDom;
+use rust_benchmark::{dom_to_test_format, fragment_to_test_format};
+use serde::{Deserialize, Serialize};
+
+/// A `GlobalAlloc` wrapper around `System` that tracks live allocated bytes
+/// and the high-water mark since the last `reset_peak`, so `benchmark_file`
+/// can report actual peak and retained memory per parse alongside timing.
+struct PeakAllocator {
+    current: AtomicUsize,
+    peak: AtomicUsize,
+}
+
+impl PeakAllocator {
+    const fn new() -> Self {
+        PeakAllocator {
+            current: AtomicUsize::new(0),
+            peak: AtomicUsize::new(0),
+        }
+    }
+
+    fn current(&self) -> usize {
+        self.current.load(Ordering::SeqCst)
+    }
 
-#[derive(Serialize)]
+    /// Reset the high-water mark to the currently live byte count, so a
+    /// later `peak()` reflects only allocations since this call.
+    fn reset_peak(&self) {
+        self.peak.store(self.current(), Ordering::SeqCst);
+    }
+
+    fn peak(&self) -> usize {
+        self.peak.load(Ordering::SeqCst)
+    }
+}
+
+unsafe impl GlobalAlloc for PeakAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let current = self.current.fetch_add(layout.size(), Ordering::SeqCst) + layout.size();
+            self.peak.fetch_max(current, Ordering::SeqCst);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        self.current.fetch_sub(layout.size(), Ordering::SeqCst);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            if new_size > layout.size() {
+                let grew_by = new_size - layout.size();
+                let current = self.current.fetch_add(grew_by, Ordering::SeqCst) + grew_by;
+                self.peak.fetch_max(current, Ordering::SeqCst);
+            } else {
+                self.current.fetch_sub(layout.size() - new_size, Ordering::SeqCst);
+            }
+        }
+        new_ptr
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: PeakAllocator = PeakAllocator::new();
+
+#[derive(Serialize, Deserialize)]
 struct BenchmarkResult {
     file: String,
+    parse_mode: String,
+    fragment_context: Option<String>,
     size_bytes: usize,
     iterations: usize,
     avg_ms: f64,
     min_ms: f64,
     max_ms: f64,
     throughput_mbs: f64,
+    peak_bytes: usize,
+    retained_bytes: usize,
+    bytes_per_input_byte: f64,
     output: String,
 }
 
-/// Serialize DOM to html5lib test format
-fn serialize_to_test_format(buf: &mut String, indent: usize, handle: &Handle) {
-    buf.push('|');
-    buf.extend(iter::repeat_n(" ", indent));
-
-    match &handle.data {
-        NodeData::Document => {
-            // Don't output anything for document node, just recurse into children
-            for child in handle.children.borrow().iter() {
-                serialize_to_test_format(buf, indent, child);
-            }
-            return;
-        }
-
-        NodeData::Doctype {
-            name,
-            public_id,
-            system_id,
-        } => {
-            buf.push_str("<!DOCTYPE ");
-            buf.push_str(name);
-            if !public_id.is_empty() || !system_id.is_empty() {
-                buf.push_str(&format!(" \"{}\" \"{}\"", public_id, system_id));
-            }
-            buf.push_str(">\n");
-        }
-
-        NodeData::Text { contents } => {
-            buf.push('"');
-            buf.push_str(&contents.borrow());
-            buf.push_str("\"\n");
-        }
-
-        NodeData::Comment { contents } => {
-            buf.push_str("<!-- ");
-            buf.push_str(contents);
-            buf.push_str(" -->\n");
-        }
-
-        NodeData::Element { name, attrs, template_contents, .. } => {
-            buf.push('<');
-            match &name.ns {
-                ns if *ns == html5ever::ns!(svg) => buf.push_str("svg "),
-                ns if *ns == html5ever::ns!(mathml) => buf.push_str("math "),
-                _ => (),
-            }
-            buf.push_str(&name.local);
-            buf.push_str(">\n");
-
-            let mut attrs_vec: Vec<_> = attrs.borrow().clone();
-            attrs_vec.sort_by(|x, y| x.name.local.cmp(&y.name.local));
-
-            for attr in attrs_vec.iter() {
-                buf.push('|');
-                buf.extend(iter::repeat_n(" ", indent + 2));
-                match &attr.name.ns {
-                    ns if *ns == html5ever::ns!(xlink) => buf.push_str("xlink "),
-                    ns if *ns == html5ever::ns!(xml) => buf.push_str("xml "),
-                    ns if *ns == html5ever::ns!(xmlns) => buf.push_str("xmlns "),
-                    _ => (),
-                }
-                buf.push_str(&format!("{}=\"{}\"\n", attr.name.local, attr.value));
-            }
-
-            // Recurse into children
-            for child in handle.children.borrow().iter() {
-                serialize_to_test_format(buf, indent + 2, child);
-            }
+/// Which `html5ever` entry point to exercise: full document parsing, or
+/// fragment parsing (e.g. for `innerHTML`) rooted at a context element.
+enum ParseMode {
+    Document,
+    Fragment(String),
+}
 
-            // Handle template contents
-            if let Some(ref content) = &*template_contents.borrow() {
-                buf.push('|');
-                buf.extend(iter::repeat_n(" ", indent + 2));
-                buf.push_str("content\n");
-                for child in content.children.borrow().iter() {
-                    serialize_to_test_format(buf, indent + 4, child);
-                }
-            }
-            return; // Already handled children
+/// Run a single parse of `html` in the given mode.
+fn parse_to_dom(html: &str, mode: &ParseMode) -> RcDom {
+    match mode {
+        ParseMode::Document => parse_document(RcDom::default(), Default::default())
+            .from_utf8()
+            .read_from(&mut html.as_bytes())
+            .unwrap(),
+        ParseMode::Fragment(context) => {
+            let context_name = QualName::new(None, html5ever::ns!(html), context.as_str().into());
+            parse_fragment(RcDom::default(), Default::default(), context_name, Vec::new())
+                .from_utf8()
+                .read_from(&mut html.as_bytes())
+                .unwrap()
         }
-
-        NodeData::ProcessingInstruction { .. } => {}
-    }
-
-    // Recurse into children (for non-element nodes)
-    for child in handle.children.borrow().iter() {
-        serialize_to_test_format(buf, indent + 2, child);
     }
 }
 
-fn dom_to_test_format(dom: &RcDom) -> String {
-    let mut result = String::new();
-    for child in dom.document.children.borrow().iter() {
-        serialize_to_test_format(&mut result, 1, child);
-    }
-    // Remove trailing newline if present
-    if result.ends_with('\n') {
-        result.pop();
+/// Serialize a parsed tree to the html5lib test format for the given mode.
+fn dom_output(dom: &RcDom, mode: &ParseMode) -> String {
+    match mode {
+        ParseMode::Document => dom_to_test_format(dom),
+        ParseMode::Fragment(_) => fragment_to_test_format(dom),
     }
-    result
 }
 
-fn benchmark_file(filepath: &Path, iterations: usize) -> BenchmarkResult {
+fn benchmark_file(filepath: &Path, iterations: usize, mode: &ParseMode) -> BenchmarkResult {
     let html = fs::read_to_string(filepath).expect("Failed to read file");
     let file_size = html.len();
 
     // Warmup
     let warmup_iterations = std::cmp::min(3, iterations / 10 + 1);
     for _ in 0..warmup_iterations {
-        let _ = parse_document(RcDom::default(), Default::default())
-            .from_utf8()
-            .read_from(&mut html.as_bytes())
-            .unwrap();
+        let _ = parse_to_dom(&html, mode);
     }
 
     // Benchmark
     let mut times = Vec::with_capacity(iterations);
     let mut dom = None;
+    let baseline_bytes = ALLOCATOR.current();
+    let mut peak_bytes = 0usize;
 
     for _ in 0..iterations {
+        // Drop the previous iteration's tree before measuring, otherwise it's
+        // still live while the next one is built and peak_bytes reports
+        // roughly two trees' worth of memory instead of one.
+        dom = None;
+        ALLOCATOR.reset_peak();
         let start = Instant::now();
-        dom = Some(
-            parse_document(RcDom::default(), Default::default())
-                .from_utf8()
-                .read_from(&mut html.as_bytes())
-                .unwrap(),
-        );
+        dom = Some(parse_to_dom(&html, mode));
         let elapsed = start.elapsed();
         times.push(elapsed.as_secs_f64());
+        peak_bytes = peak_bytes.max(ALLOCATOR.peak().saturating_sub(baseline_bytes));
     }
 
-    // Get output for comparison
-    let output = dom.map(|d| dom_to_test_format(&d)).unwrap_or_default();
+    // Get output for comparison, and the bytes still retained by the last
+    // constructed tree before it's dropped.
+    let retained_bytes = ALLOCATOR.current().saturating_sub(baseline_bytes);
+    let output = dom.as_ref().map(|d| dom_output(d, mode)).unwrap_or_default();
 
     let avg_time: f64 = times.iter().sum::<f64>() / times.len() as f64;
     let min_time: f64 = times.iter().cloned().fold(f64::INFINITY, f64::min);
     let max_time: f64 = times.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
     let throughput = file_size as f64 / avg_time / 1_000_000.0; // MB/s
+    let bytes_per_input_byte = if file_size == 0 {
+        0.0
+    } else {
+        peak_bytes as f64 / file_size as f64
+    };
+
+    let (parse_mode, fragment_context) = match mode {
+        ParseMode::Document => ("document".to_string(), None),
+        ParseMode::Fragment(context) => ("fragment".to_string(), Some(context.clone())),
+    };
 
     BenchmarkResult {
         file: filepath.file_name().unwrap().to_string_lossy().to_string(),
+        parse_mode,
+        fragment_context,
         size_bytes: file_size,
         iterations,
         avg_ms: avg_time * 1000.0,
         min_ms: min_time * 1000.0,
         max_ms: max_time * 1000.0,
         throughput_mbs: throughput,
+        peak_bytes,
+        retained_bytes,
+        bytes_per_input_byte,
         output,
     }
 }
@@ -193,6 +213,149 @@ fn collect_html_files(directory: &Path) -> Vec<(std::path::PathBuf, String, usiz
     files
 }
 
+/// Output report format, selected independently of which directories get
+/// scanned (mirrors how coverage tools like grcov pick lcov/cobertura/etc.).
+enum OutputFormat {
+    Json,
+    Csv,
+    Junit,
+}
+
+impl OutputFormat {
+    fn parse(name: &str) -> OutputFormat {
+        match name {
+            "json" => OutputFormat::Json,
+            "csv" => OutputFormat::Csv,
+            "junit" => OutputFormat::Junit,
+            other => panic!("unknown --format {other:?} (expected json, csv, or junit)"),
+        }
+    }
+}
+
+struct CliArgs {
+    mode: ParseMode,
+    format: OutputFormat,
+    baseline: Option<PathBuf>,
+}
+
+/// Parse `--fragment <context>`, `--format <json|csv|junit>`, and
+/// `--baseline <path>` off the command line.
+fn parse_cli_args() -> CliArgs {
+    let mut mode = ParseMode::Document;
+    let mut format = OutputFormat::Json;
+    let mut baseline = None;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--fragment" => {
+                let context = args
+                    .next()
+                    .expect("--fragment requires a context element name");
+                mode = ParseMode::Fragment(context);
+            }
+            "--format" => {
+                let name = args.next().expect("--format requires a value");
+                format = OutputFormat::parse(&name);
+            }
+            "--baseline" => {
+                let path = args.next().expect("--baseline requires a file path");
+                baseline = Some(PathBuf::from(path));
+            }
+            other => panic!("unrecognized argument {other:?}"),
+        }
+    }
+
+    CliArgs {
+        mode,
+        format,
+        baseline,
+    }
+}
+
+/// Load a prior JSON run, keyed by file name, for JUnit failure detection.
+fn load_baseline(path: &Path) -> HashMap<String, String> {
+    let json = fs::read_to_string(path).expect("failed to read baseline file");
+    let results: Vec<BenchmarkResult> =
+        serde_json::from_str(&json).expect("failed to parse baseline JSON");
+    results.into_iter().map(|r| (r.file, r.output)).collect()
+}
+
+fn render_csv(results: &[BenchmarkResult]) -> String {
+    let mut csv = String::from(
+        "file,parse_mode,fragment_context,size_bytes,iterations,avg_ms,min_ms,max_ms,throughput_mbs,peak_bytes,retained_bytes,bytes_per_input_byte\n",
+    );
+    for r in results {
+        csv.push_str(&format!(
+            "{},{},{},{},{},{},{},{},{},{},{},{}\n",
+            csv_field(&r.file),
+            r.parse_mode,
+            csv_field(r.fragment_context.as_deref().unwrap_or("")),
+            r.size_bytes,
+            r.iterations,
+            r.avg_ms,
+            r.min_ms,
+            r.max_ms,
+            r.throughput_mbs,
+            r.peak_bytes,
+            r.retained_bytes,
+            r.bytes_per_input_byte,
+        ));
+    }
+    csv
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains(',') || value.contains('"') || value.contains('\n') {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Render results as a JUnit XML `<testsuite>`, one `<testcase>` per file,
+/// so the benchmark can plug into CI test-report upload pipelines. A
+/// `<failure>` is emitted when `output` diverges from `baseline`.
+fn render_junit(results: &[BenchmarkResult], baseline: &HashMap<String, String>) -> String {
+    let failures = results
+        .iter()
+        .filter(|r| baseline.get(&r.file).is_some_and(|expected| expected != &r.output))
+        .count();
+
+    let mut xml = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuite name=\"rust_benchmark\" tests=\"{}\" failures=\"{}\">\n",
+        results.len(),
+        failures
+    );
+    for r in results {
+        xml.push_str(&format!(
+            "  <testcase classname=\"rust_benchmark\" name=\"{}\" time=\"{}\">\n",
+            xml_escape(&r.file),
+            r.avg_ms / 1000.0
+        ));
+        if let Some(expected) = baseline.get(&r.file) {
+            if expected != &r.output {
+                xml.push_str(&format!(
+                    "    <failure message=\"output diverged from baseline\">expected:\n{}\nactual:\n{}</failure>\n",
+                    xml_escape(expected),
+                    xml_escape(&r.output)
+                ));
+            }
+        }
+        xml.push_str("  </testcase>\n");
+    }
+    xml.push_str("</testsuite>\n");
+    xml
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
 fn main() {
     let script_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
     let samples_dir = script_dir.join("../samples");
@@ -203,6 +366,8 @@ fn main() {
         std::process::exit(1);
     }
 
+    let cli = parse_cli_args();
+
     let mut all_files = collect_html_files(&samples_dir);
     all_files.extend(collect_html_files(&test_files_dir));
 
@@ -227,7 +392,7 @@ fn main() {
             "Benchmarking {} ({} bytes, {} iterations)...",
             filename, file_size, iterations
         );
-        let result = benchmark_file(&filepath, iterations);
+        let result = benchmark_file(&filepath, iterations, &cli.mode);
         eprintln!(
             "  Average: {:.2} ms, Throughput: {:.2} MB/s",
             result.avg_ms, result.throughput_mbs
@@ -235,7 +400,19 @@ fn main() {
         results.push(result);
     }
 
-    // Output JSON to stdout
-    let json = serde_json::to_string_pretty(&results).expect("Failed to serialize results");
-    println!("{}", json);
+    match cli.format {
+        OutputFormat::Json => {
+            let json = serde_json::to_string_pretty(&results).expect("Failed to serialize results");
+            println!("{}", json);
+        }
+        OutputFormat::Csv => print!("{}", render_csv(&results)),
+        OutputFormat::Junit => {
+            let baseline = cli
+                .baseline
+                .as_deref()
+                .map(load_baseline)
+                .unwrap_or_default();
+            print!("{}", render_junit(&results, &baseline));
+        }
+    }
 }